@@ -27,6 +27,26 @@ use crate::platform_impl::{
     Window as LinuxWindow,
 };
 
+// UNINTEGRATED SCAFFOLDING: `xkb_keysyms::XkbState` has no call sites anywhere in this crate.
+// It does not back `KeyCodeExtScancode`, and no Wayland/X11 event path feeds keycodes through
+// it yet, so layout-aware keysyms, text and compose sequences are not actually available. Kept
+// crate-private (not re-exported) so it can't be mistaken for a shipped public API.
+#[cfg(any(feature = "x11", feature = "wayland"))]
+mod xkb_ffi;
+#[cfg(any(feature = "x11", feature = "wayland"))]
+mod xkb_keysyms;
+#[cfg(any(feature = "x11", feature = "wayland"))]
+#[allow(unused_imports)]
+pub(crate) use xkb_keysyms::XkbState;
+
+// MIT-SHM (X11) / `wl_shm` (Wayland) backed blits for `WindowExtUnix::present_buffer`.
+#[cfg(any(feature = "x11", feature = "wayland"))]
+mod shm;
+
+// `_NET_WM_ICON` encoding/writing backing `with_x11_icon`/`set_x11_icon`.
+#[cfg(feature = "x11")]
+mod icon;
+
 // TODO: stupid hack so that glutin can do its work
 #[doc(hidden)]
 #[cfg(feature = "x11")]
@@ -56,6 +76,15 @@ pub trait EventLoopWindowTargetExtUnix {
     /// The pointer will become invalid when the winit `EventLoop` is destroyed.
     #[cfg(feature = "wayland")]
     fn wayland_display(&self) -> Option<*mut raw::c_void>;
+
+    /// Returns the backend that was chosen to back this `EventLoopWindowTarget`.
+    ///
+    /// This is most useful after calling
+    /// [`new_with_backend_preference`](EventLoopExtUnix::new_with_backend_preference), where the
+    /// chosen backend otherwise can only be inferred from [`is_wayland`](Self::is_wayland) /
+    /// [`is_x11`](Self::is_x11).
+    #[cfg(any(feature = "x11", feature = "wayland"))]
+    fn backend(&self) -> Backend;
 }
 
 impl<T> EventLoopWindowTargetExtUnix for EventLoopWindowTarget<T> {
@@ -93,6 +122,78 @@ impl<T> EventLoopWindowTargetExtUnix for EventLoopWindowTarget<T> {
             _ => None,
         }
     }
+
+    #[inline]
+    #[cfg(any(feature = "x11", feature = "wayland"))]
+    fn backend(&self) -> Backend {
+        match self.p {
+            #[cfg(feature = "wayland")]
+            LinuxEventLoopWindowTarget::Wayland(_) => Backend::Wayland,
+            #[cfg(feature = "x11")]
+            LinuxEventLoopWindowTarget::X(_) => Backend::X11,
+        }
+    }
+}
+
+/// The windowing backend used by an `EventLoop` on Unix-like systems.
+#[cfg(any(feature = "x11", feature = "wayland"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The Wayland backend.
+    #[cfg(feature = "wayland")]
+    Wayland,
+    /// The X11 backend.
+    #[cfg(feature = "x11")]
+    X11,
+}
+
+/// The error returned by
+/// [`new_with_backend_preference`](EventLoopExtUnix::new_with_backend_preference) and
+/// [`new_with_backend_preference_any_thread`](EventLoopExtUnix::new_with_backend_preference_any_thread)
+/// when none of the requested backends could be initialized.
+#[cfg(any(feature = "x11", feature = "wayland"))]
+#[derive(Debug)]
+pub struct BackendError {
+    /// The error returned by each backend that was tried, in the order they were tried.
+    pub errors: Vec<(Backend, String)>,
+}
+
+#[cfg(any(feature = "x11", feature = "wayland"))]
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to initialize any of the requested backends:")?;
+        for (backend, err) in &self.errors {
+            write!(f, " {:?}: {};", backend, err)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "x11", feature = "wayland"))]
+impl std::error::Error for BackendError {}
+
+#[cfg(any(feature = "x11", feature = "wayland"))]
+fn try_backend<T>(backend: Backend, any_thread: bool) -> Result<LinuxEventLoop<T>, String> {
+    match backend {
+        #[cfg(feature = "wayland")]
+        Backend::Wayland => {
+            if any_thread {
+                LinuxEventLoop::new_wayland_any_thread()
+            } else {
+                LinuxEventLoop::new_wayland()
+            }
+            .map_err(|err| err.to_string())
+        }
+        #[cfg(feature = "x11")]
+        Backend::X11 => {
+            if any_thread {
+                LinuxEventLoop::new_x11_any_thread()
+            } else {
+                LinuxEventLoop::new_x11()
+            }
+            .map_err(|err| err.to_string())
+        }
+    }
 }
 
 /// Additional methods on `EventLoop` that are specific to Unix.
@@ -144,6 +245,40 @@ pub trait EventLoopExtUnix {
     fn new_wayland_any_thread() -> Self
     where
         Self: Sized;
+
+    /// Builds a new `EventLoop`, trying each backend in `order` in turn and succeeding with the
+    /// first one that initializes, e.g. `new_with_backend_preference(&[Backend::Wayland,
+    /// Backend::X11])` to prefer Wayland but transparently fall back to X11.
+    ///
+    /// Use [`EventLoopWindowTargetExtUnix::backend`] to find out which backend was chosen.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BackendError`] if none of the backends in `order` could be initialized.
+    ///
+    /// # Panics
+    ///
+    /// If called outside the main thread. To initialize an event loop outside the main thread,
+    /// use
+    /// [`new_with_backend_preference_any_thread`](#tymethod.new_with_backend_preference_any_thread).
+    #[cfg(any(feature = "x11", feature = "wayland"))]
+    fn new_with_backend_preference(order: &[Backend]) -> Result<Self, BackendError>
+    where
+        Self: Sized;
+
+    /// Builds a new `EventLoop` on any thread, trying each backend in `order` in turn and
+    /// succeeding with the first one that initializes.
+    ///
+    /// This method bypasses the cross-platform compatibility requirement
+    /// that `EventLoop` be created on the main thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BackendError`] if none of the backends in `order` could be initialized.
+    #[cfg(any(feature = "x11", feature = "wayland"))]
+    fn new_with_backend_preference_any_thread(order: &[Backend]) -> Result<Self, BackendError>
+    where
+        Self: Sized;
 }
 
 fn wrap_ev<T>(event_loop: LinuxEventLoop<T>) -> EventLoop<T> {
@@ -190,6 +325,32 @@ impl<T> EventLoopExtUnix for EventLoop<T> {
                 .expect("failed to open Wayland connection"),
         )
     }
+
+    #[inline]
+    #[cfg(any(feature = "x11", feature = "wayland"))]
+    fn new_with_backend_preference(order: &[Backend]) -> Result<Self, BackendError> {
+        let mut errors = Vec::new();
+        for &backend in order {
+            match try_backend(backend, false) {
+                Ok(event_loop) => return Ok(wrap_ev(event_loop)),
+                Err(err) => errors.push((backend, err)),
+            }
+        }
+        Err(BackendError { errors })
+    }
+
+    #[inline]
+    #[cfg(any(feature = "x11", feature = "wayland"))]
+    fn new_with_backend_preference_any_thread(order: &[Backend]) -> Result<Self, BackendError> {
+        let mut errors = Vec::new();
+        for &backend in order {
+            match try_backend(backend, true) {
+                Ok(event_loop) => return Ok(wrap_ev(event_loop)),
+                Err(err) => errors.push((backend, err)),
+            }
+        }
+        Err(BackendError { errors })
+    }
 }
 
 /// Additional methods on `Window` that are specific to Unix.
@@ -223,6 +384,18 @@ pub trait WindowExtUnix {
     #[cfg(feature = "x11")]
     fn xcb_connection(&self) -> Option<*mut raw::c_void>;
 
+    /// Sets the window icon shown by the window manager, overwriting any icon set with
+    /// [`WindowBuilderExtUnix::with_x11_icon`]. Only relevant on X11.
+    ///
+    /// `icons` holds one `(rgba, width, height)` triple per size the window manager can choose
+    /// between; pass an empty slice to clear the icon. Each `rgba` holds non-premultiplied,
+    /// non-linear RGBA pixels (4 bytes each, in R, G, B, A order), `width * height` of them, in
+    /// row-major order.
+    ///
+    /// Does nothing if the window doesn't use X11 (if it uses Wayland for example).
+    #[cfg(feature = "x11")]
+    fn set_x11_icon(&self, icons: &[(Vec<u8>, u32, u32)]);
+
     /// Returns a pointer to the `wl_surface` object of wayland that is used by this window.
     ///
     /// Returns `None` if the window doesn't use wayland (if it uses xlib for example).
@@ -243,6 +416,35 @@ pub trait WindowExtUnix {
     #[cfg(feature = "wayland")]
     fn set_wayland_theme<T: Theme>(&self, theme: T);
 
+    /// Presents an ARGB8888 software-rendered buffer to the window, without going through a
+    /// GPU API.
+    ///
+    /// `stride` is the number of pixels (not bytes) between the start of each row in `buffer`,
+    /// so callers can present a sub-region of a larger buffer without copying it first.
+    ///
+    /// On X11 this is backed by the MIT-SHM extension, falling back to a plain `XPutImage` when
+    /// the X server doesn't advertise SHM support. On Wayland this attaches a `wl_buffer` backed
+    /// by a double-buffered `wl_shm_pool` to the window's `wl_surface` and commits it, honoring
+    /// the surface's scale factor.
+    ///
+    /// `buffer` must hold at least `(height - 1) * stride + width` pixels; returns `false`
+    /// without presenting anything if it's shorter than that, instead of indexing out of bounds.
+    fn present_buffer(&self, buffer: &[u32], width: u32, height: u32, stride: u32) -> bool;
+
+    /// Resizes the shared-memory surface backing [`present_buffer`](Self::present_buffer).
+    ///
+    /// Call this before presenting a buffer sized for the window's new dimensions, e.g. in
+    /// response to a [`WindowEvent::Resized`](crate::event::WindowEvent::Resized).
+    fn resize_surface(&self, width: u32, height: u32);
+
+    /// Drops the shared-memory backing store behind [`present_buffer`](Self::present_buffer), if
+    /// one has been allocated.
+    ///
+    /// Call this while handling the window's destruction/close so its SHM segment (X11) or
+    /// `wl_shm_pool` (Wayland) is released right away instead of lingering in this thread's
+    /// surface cache until the thread itself tears down.
+    fn destroy_shm_surface(&self);
+
     /// Check if the window is ready for drawing
     ///
     /// It is a remnant of a previous implementation detail for the
@@ -305,6 +507,18 @@ impl WindowExtUnix for Window {
         }
     }
 
+    #[inline]
+    #[cfg(feature = "x11")]
+    fn set_x11_icon(&self, icons: &[(Vec<u8>, u32, u32)]) {
+        match self.window {
+            LinuxWindow::X(ref w) => unsafe {
+                icon::set_icons(w.xlib_display(), w.xlib_window(), icons)
+            },
+            #[cfg(feature = "wayland")]
+            _ => {}
+        }
+    }
+
     #[inline]
     #[cfg(feature = "wayland")]
     fn wayland_surface(&self) -> Option<*mut raw::c_void> {
@@ -335,6 +549,73 @@ impl WindowExtUnix for Window {
         }
     }
 
+    #[inline]
+    fn present_buffer(&self, buffer: &[u32], width: u32, height: u32, stride: u32) -> bool {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => unsafe {
+                shm::x11::present_buffer(
+                    w.xlib_display(),
+                    w.xlib_window(),
+                    w.xlib_screen_id(),
+                    buffer,
+                    width,
+                    height,
+                    stride,
+                )
+            },
+            #[cfg(feature = "wayland")]
+            LinuxWindow::Wayland(ref w) => unsafe {
+                shm::wayland::present_buffer(
+                    w.display().get_display_ptr() as *mut _,
+                    w.surface().as_ref().c_ptr() as *mut _,
+                    buffer,
+                    width,
+                    height,
+                    stride,
+                    self.scale_factor().round() as i32,
+                )
+            },
+        }
+    }
+
+    #[inline]
+    fn resize_surface(&self, width: u32, height: u32) {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => unsafe {
+                shm::x11::resize_surface(
+                    w.xlib_display(),
+                    w.xlib_window(),
+                    w.xlib_screen_id(),
+                    width,
+                    height,
+                )
+            },
+            #[cfg(feature = "wayland")]
+            LinuxWindow::Wayland(ref w) => unsafe {
+                shm::wayland::resize_surface(
+                    w.display().get_display_ptr() as *mut _,
+                    w.surface().as_ref().c_ptr() as *mut _,
+                    width,
+                    height,
+                )
+            },
+        }
+    }
+
+    #[inline]
+    fn destroy_shm_surface(&self) {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => shm::x11::drop_surface(w.xlib_window()),
+            #[cfg(feature = "wayland")]
+            LinuxWindow::Wayland(ref w) => {
+                shm::wayland::drop_surface(w.surface().as_ref().c_ptr() as *mut _)
+            }
+        }
+    }
+
     #[inline]
     fn is_ready(&self) -> bool {
         true
@@ -367,11 +648,30 @@ pub trait WindowBuilderExtUnix {
     #[cfg(feature = "x11")]
     fn with_base_size<S: Into<Size>>(self, base_size: S) -> Self;
 
+    /// Adds an icon to the window's `_NET_WM_ICON` hint, the icon the window manager shows in
+    /// the taskbar, alt-tab switcher, etc. Only relevant on X11.
+    ///
+    /// `rgba` holds non-premultiplied, non-linear RGBA pixels (4 bytes each, in R, G, B, A
+    /// order), `width * height` of them, in row-major order. Call this once per size you have available (e.g. once with a 16x16 and
+    /// once with a 48x48 icon) — each call appends another size to the property so the window
+    /// manager can pick the best one for the context it's drawing in, rather than replacing a
+    /// previous call's icon.
+    ///
+    /// Wayland has no equivalent per-surface icon protocol; use
+    /// [`with_app_id`](Self::with_app_id) there and ship a `.desktop` file referencing an icon
+    /// instead, so the two backends present a coherent icon story behind one builder surface.
+    #[cfg(feature = "x11")]
+    fn with_x11_icon(self, rgba: Vec<u8>, width: u32, height: u32) -> Self;
+
     /// Build window with a given application ID. It should match the `.desktop` file distributed with
     /// your program. Only relevant on Wayland.
     ///
     /// For details about application ID conventions, see the
     /// [Desktop Entry Spec](https://specifications.freedesktop.org/desktop-entry-spec/desktop-entry-spec-latest.html#desktop-file-id)
+    ///
+    /// The compositor takes the window's icon from the `.desktop` file matched by this ID; there
+    /// is no separate Wayland icon hint to set. On X11, pair this with
+    /// [`with_x11_icon`](Self::with_x11_icon) to get the same icon there.
     #[cfg(feature = "wayland")]
     fn with_app_id(self, app_id: String) -> Self;
 }
@@ -436,6 +736,13 @@ impl WindowBuilderExtUnix for WindowBuilder {
         self
     }
 
+    #[inline]
+    #[cfg(feature = "x11")]
+    fn with_x11_icon(mut self, rgba: Vec<u8>, width: u32, height: u32) -> Self {
+        self.platform_specific.x11_icons.push((rgba, width, height));
+        self
+    }
+
     #[inline]
     #[cfg(feature = "wayland")]
     fn with_app_id(mut self, app_id: String) -> Self {
@@ -532,9 +839,106 @@ pub struct ARGBColor {
     pub b: u8,
 }
 
+// NOTE: this table only resolves the *physical* `KeyCode` (the evdev scancode) and is untouched
+// by the `xkb_keysyms` module above. `XkbState` is unintegrated scaffolding with no call sites:
+// nothing in this crate builds it from a `wl_keyboard.keymap` fd or an X11 device, and no event
+// path feeds keycodes through it. Layout-aware keysyms, text and compose sequences do NOT work
+// yet — do not rely on `XkbState` for anything beyond reviewing the planned shape of that work.
 impl KeyCodeExtScancode for KeyCode {
     fn to_scancode(self) -> Option<u32> {
         match self {
+            KeyCode::Escape => Some(1),
+            KeyCode::Digit1 => Some(2),
+            KeyCode::Digit2 => Some(3),
+            KeyCode::Digit3 => Some(4),
+            KeyCode::Digit4 => Some(5),
+            KeyCode::Digit5 => Some(6),
+            KeyCode::Digit6 => Some(7),
+            KeyCode::Digit7 => Some(8),
+            KeyCode::Digit8 => Some(9),
+            KeyCode::Digit9 => Some(10),
+            KeyCode::Digit0 => Some(11),
+            KeyCode::Minus => Some(12),
+            KeyCode::Equal => Some(13),
+            KeyCode::Backspace => Some(14),
+            KeyCode::Tab => Some(15),
+            KeyCode::KeyQ => Some(16),
+            KeyCode::KeyW => Some(17),
+            KeyCode::KeyE => Some(18),
+            KeyCode::KeyR => Some(19),
+            KeyCode::KeyT => Some(20),
+            KeyCode::KeyY => Some(21),
+            KeyCode::KeyU => Some(22),
+            KeyCode::KeyI => Some(23),
+            KeyCode::KeyO => Some(24),
+            KeyCode::KeyP => Some(25),
+            KeyCode::BracketLeft => Some(26),
+            KeyCode::BracketRight => Some(27),
+            KeyCode::Enter => Some(28),
+            KeyCode::ControlLeft => Some(29),
+            KeyCode::KeyA => Some(30),
+            KeyCode::KeyS => Some(31),
+            KeyCode::KeyD => Some(32),
+            KeyCode::KeyF => Some(33),
+            KeyCode::KeyG => Some(34),
+            KeyCode::KeyH => Some(35),
+            KeyCode::KeyJ => Some(36),
+            KeyCode::KeyK => Some(37),
+            KeyCode::KeyL => Some(38),
+            KeyCode::Semicolon => Some(39),
+            KeyCode::Backquote => Some(41),
+            KeyCode::ShiftLeft => Some(42),
+            KeyCode::Backslash => Some(43),
+            KeyCode::KeyZ => Some(44),
+            KeyCode::KeyX => Some(45),
+            KeyCode::KeyC => Some(46),
+            KeyCode::KeyV => Some(47),
+            KeyCode::KeyB => Some(48),
+            KeyCode::KeyN => Some(49),
+            KeyCode::KeyM => Some(50),
+            KeyCode::Comma => Some(51),
+            KeyCode::Period => Some(52),
+            KeyCode::Slash => Some(53),
+            KeyCode::ShiftRight => Some(54),
+            KeyCode::AltLeft => Some(56),
+            KeyCode::Space => Some(57),
+            KeyCode::CapsLock => Some(58),
+            KeyCode::F1 => Some(59),
+            KeyCode::F2 => Some(60),
+            KeyCode::F3 => Some(61),
+            KeyCode::F4 => Some(62),
+            KeyCode::F5 => Some(63),
+            KeyCode::F6 => Some(64),
+            KeyCode::F7 => Some(65),
+            KeyCode::F8 => Some(66),
+            KeyCode::F9 => Some(67),
+            KeyCode::F10 => Some(68),
+            KeyCode::ScrollLock => Some(70),
+            KeyCode::F11 => Some(87),
+            KeyCode::F12 => Some(88),
+            KeyCode::ControlRight => Some(97),
+            KeyCode::PrintScreen => Some(99),
+            KeyCode::AltRight => Some(100),
+            KeyCode::Home => Some(102),
+            KeyCode::ArrowUp => Some(103),
+            KeyCode::PageUp => Some(104),
+            KeyCode::ArrowLeft => Some(105),
+            KeyCode::ArrowRight => Some(106),
+            KeyCode::End => Some(107),
+            KeyCode::ArrowDown => Some(108),
+            KeyCode::PageDown => Some(109),
+            KeyCode::Insert => Some(110),
+            KeyCode::Delete => Some(111),
+            KeyCode::AudioVolumeMute => Some(113),
+            KeyCode::AudioVolumeDown => Some(114),
+            KeyCode::AudioVolumeUp => Some(115),
+            KeyCode::Pause => Some(119),
+            KeyCode::SuperLeft => Some(125),
+            KeyCode::ContextMenu => Some(127),
+            KeyCode::Fn => Some(143),
+            KeyCode::BrowserBack => Some(158),
+            KeyCode::BrowserForward => Some(159),
+            KeyCode::Unidentified(NativeKeyCode::XKB(scancode)) => Some(scancode),
             _ => None,
         }
     }
@@ -637,3 +1041,40 @@ impl KeyCodeExtScancode for KeyCode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every evdev code that `from_scancode` maps to a known `KeyCode`; kept in sync with the
+    // `match` arms above rather than re-deriving them, so a mistake in one doesn't mask a
+    // mistake in the other.
+    const KNOWN_SCANCODES: &[u32] = &[
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 41, 42, 43, 44, 45, 46, 47, 48,
+        49, 50, 51, 52, 53, 54, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 70, 87, 88,
+        97, 99, 100, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 113, 114, 115, 119, 125,
+        127, 143, 158, 159,
+    ];
+
+    #[test]
+    fn to_scancode_is_the_inverse_of_from_scancode() {
+        for &scancode in KNOWN_SCANCODES {
+            let key_code = KeyCode::from_scancode(scancode);
+            assert_eq!(
+                key_code.to_scancode(),
+                Some(scancode),
+                "KeyCode::from_scancode({}) => {:?}, but to_scancode() didn't round-trip",
+                scancode,
+                key_code,
+            );
+        }
+    }
+
+    #[test]
+    fn to_scancode_round_trips_unidentified_codes() {
+        let key_code = KeyCode::from_scancode(12345);
+        assert_eq!(key_code, KeyCode::Unidentified(NativeKeyCode::XKB(12345)));
+        assert_eq!(key_code.to_scancode(), Some(12345));
+    }
+}
@@ -0,0 +1,105 @@
+//! Builds and writes the `_NET_WM_ICON` window property backing
+//! [`super::super::WindowBuilderExtUnix::with_x11_icon`]/
+//! [`super::super::WindowExtUnix::set_x11_icon`].
+//!
+//! The property is an array of `CARDINAL`s: one or more `width, height, pixels...` blocks
+//! concatenated back to back, pixels as non-premultiplied `0xAARRGGBB` in row-major order, one
+//! block per icon size the window manager can choose between.
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::os::raw::{c_char, c_int, c_long, c_uchar, c_ulong, c_void};
+use std::ptr;
+
+const PROP_MODE_REPLACE: c_int = 0;
+const XA_CARDINAL: c_ulong = 6;
+
+#[link(name = "X11")]
+extern "C" {
+    fn XInternAtom(display: *mut c_void, name: *const c_char, only_if_exists: c_int) -> c_ulong;
+    fn XChangeProperty(
+        display: *mut c_void,
+        window: c_ulong,
+        property: c_ulong,
+        ty: c_ulong,
+        format: c_int,
+        mode: c_int,
+        data: *const c_uchar,
+        nelements: c_int,
+    ) -> c_int;
+    fn XFlush(display: *mut c_void) -> c_int;
+}
+
+/// Encodes `icons` into the flat `CARDINAL` array `_NET_WM_ICON` expects: each icon contributes
+/// `2 + width * height` elements (its `width`, `height`, then its pixels).
+///
+/// Per-element type is `c_long` (not `u32`) because Xlib's 32-bit-format properties are always
+/// passed as one native `long` per element, zero-extended on LP64 platforms.
+pub fn encode_net_wm_icon(icons: &[(Vec<u8>, u32, u32)]) -> Vec<c_long> {
+    let mut data = Vec::new();
+    for (rgba, width, height) in icons {
+        debug_assert_eq!(rgba.len(), *width as usize * *height as usize * 4);
+        data.push(*width as c_long);
+        data.push(*height as c_long);
+        for pixel in rgba.chunks_exact(4) {
+            let (r, g, b, a) = (pixel[0], pixel[1], pixel[2], pixel[3]);
+            let argb = u32::from_be_bytes([a, r, g, b]);
+            data.push(argb as c_long);
+        }
+    }
+    data
+}
+
+/// Writes `icons` as the window's `_NET_WM_ICON` property, or clears it if `icons` is empty.
+pub unsafe fn set_icons(display: *mut c_void, window: c_ulong, icons: &[(Vec<u8>, u32, u32)]) {
+    let atom_name = b"_NET_WM_ICON\0";
+    let property = XInternAtom(display, atom_name.as_ptr() as *const c_char, 0);
+
+    let data = encode_net_wm_icon(icons);
+    XChangeProperty(
+        display,
+        window,
+        property,
+        XA_CARDINAL,
+        32,
+        PROP_MODE_REPLACE,
+        if data.is_empty() {
+            ptr::null()
+        } else {
+            data.as_ptr() as *const c_uchar
+        },
+        data.len() as c_int,
+    );
+    XFlush(display);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_header_and_pixels_per_icon() {
+        // A 1x1 opaque red icon, then a 1x2 opaque green icon.
+        let red = vec![0xff, 0x00, 0x00, 0xff];
+        let green_column = vec![0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff];
+        let data = encode_net_wm_icon(&[(red, 1, 1), (green_column, 1, 2)]);
+
+        assert_eq!(
+            data,
+            vec![
+                1,
+                1,
+                0xffff0000u32 as c_long,
+                1,
+                2,
+                0xff00ff00u32 as c_long,
+                0xff00ff00u32 as c_long,
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_icon_list_encodes_to_nothing() {
+        assert!(encode_net_wm_icon(&[]).is_empty());
+    }
+}
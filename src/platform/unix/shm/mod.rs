@@ -0,0 +1,7 @@
+//! Shared-memory backed software framebuffer presentation backing
+//! `WindowExtUnix::present_buffer`/`resize_surface`.
+
+#[cfg(feature = "x11")]
+pub mod x11;
+#[cfg(feature = "wayland")]
+pub mod wayland;
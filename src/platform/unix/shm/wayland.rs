@@ -0,0 +1,226 @@
+//! `wl_shm`-backed double buffering for `WindowExtUnix::present_buffer` on Wayland.
+//!
+//! Binding `wl_shm` requires a registry round-trip, which we run on a private
+//! [`wayland_client::EventQueue`] so as not to disturb the dispatch loop the rest of the
+//! Wayland backend already drives on the same connection. The same private queue is dispatched
+//! again before reusing a buffer slot, so we block until the compositor sends its `release`
+//! event rather than writing into memory it may still be reading.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::raw::c_void;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::rc::Rc;
+
+use wayland_client::protocol::{wl_buffer, wl_shm, wl_shm_pool, wl_surface};
+use wayland_client::{Display, EventQueue, GlobalManager, Main};
+
+struct Surface {
+    // Kept around so we can dispatch it while waiting for a `wl_buffer.release`.
+    queue: EventQueue,
+    pool: Main<wl_shm_pool::WlShmPool>,
+    file: File,
+    // Double buffered so the compositor can keep reading the previous frame while we map and
+    // write the next one. `released[i]` starts `true` (nothing attached yet), is cleared the
+    // moment `buffers[i]` is attached, and is set again by its `release` listener once the
+    // compositor is done reading it.
+    buffers: [Main<wl_buffer::WlBuffer>; 2],
+    released: [Rc<Cell<bool>>; 2],
+    front: usize,
+    width: u32,
+    height: u32,
+}
+
+thread_local! {
+    // Keyed by the `wl_surface` pointer: `present_buffer`/`resize_surface` are called
+    // repeatedly against the same window, and we want to reuse the pool across frames rather
+    // than reallocate shared memory on every call.
+    static SURFACES: RefCell<HashMap<usize, Surface>> = RefCell::new(HashMap::new());
+}
+
+fn bind_shm(display_ptr: *mut c_void) -> Option<(EventQueue, Main<wl_shm::WlShm>)> {
+    let display = unsafe { Display::from_external_display(display_ptr as *mut _) };
+    let mut queue = display.create_event_queue();
+    let attached = display.attach(queue.token());
+    let globals = GlobalManager::new(&attached);
+    queue.sync_roundtrip(&mut (), |_, _, _| {}).ok()?;
+    let shm = globals.instantiate_exact::<wl_shm::WlShm>(1).ok()?;
+    Some((queue, shm))
+}
+
+unsafe fn create_surface(display_ptr: *mut c_void, width: u32, height: u32) -> Option<Surface> {
+    let (queue, shm) = bind_shm(display_ptr)?;
+
+    let stride = width as i32 * 4;
+    let frame_len = (stride * height as i32) as u64;
+    let len = frame_len * 2;
+
+    let fd = libc::memfd_create(b"winit-wl-shm\0".as_ptr() as *const _, libc::MFD_CLOEXEC);
+    if fd < 0 {
+        return None;
+    }
+    let mut file = File::from_raw_fd(fd);
+    file.set_len(len as u64).ok()?;
+
+    let pool = shm.create_pool(file.as_raw_fd(), len as i32);
+    let mut make_buffer = |offset: i32| {
+        let released = Rc::new(Cell::new(true));
+        let buffer = pool.create_buffer(
+            offset,
+            width as i32,
+            height as i32,
+            stride,
+            wl_shm::Format::Argb8888,
+        );
+        let released_in_listener = released.clone();
+        buffer.quick_assign(move |_, event, _| {
+            if let wl_buffer::Event::Release = event {
+                released_in_listener.set(true);
+            }
+        });
+        (buffer, released)
+    };
+    let (buffer_a, released_a) = make_buffer(0);
+    let (buffer_b, released_b) = make_buffer(frame_len as i32);
+
+    Some(Surface {
+        queue,
+        pool,
+        file,
+        buffers: [buffer_a, buffer_b],
+        released: [released_a, released_b],
+        front: 0,
+        width,
+        height,
+    })
+}
+
+/// Whether `buffer_len` pixels are enough to read a `width`x`height` image at `stride` pixels
+/// per row — i.e. whether the row-copy loop in `blit` can run without reading out of bounds.
+fn copy_rows_fits(buffer_len: usize, width: u32, height: u32, stride: u32) -> bool {
+    if height == 0 {
+        return true;
+    }
+    if stride < width {
+        return false;
+    }
+    match (height as usize - 1)
+        .checked_mul(stride as usize)
+        .and_then(|rows| rows.checked_add(width as usize))
+    {
+        Some(required) => buffer_len >= required,
+        None => false,
+    }
+}
+
+unsafe fn blit(
+    surface: &mut Surface,
+    wl_surface: *mut c_void,
+    buffer: &[u32],
+    width: u32,
+    height: u32,
+    stride: u32,
+    scale_factor: i32,
+) -> bool {
+    if !copy_rows_fits(buffer.len(), width, height, stride) {
+        return false;
+    }
+
+    // Block until the compositor releases this slot's previous contents; otherwise we'd be
+    // writing into memory it may still be compositing from.
+    let released = surface.released[surface.front].clone();
+    while !released.get() {
+        if surface.queue.dispatch(&mut (), |_, _, _| {}).is_err() {
+            break;
+        }
+    }
+
+    let frame_len = (width as usize * height as usize * 4) as u64;
+    let offset = surface.front as u64 * frame_len;
+
+    surface.file.seek(SeekFrom::Start(offset)).ok();
+    for row in 0..height as usize {
+        let src = &buffer[row * stride as usize..row * stride as usize + width as usize];
+        let bytes = std::slice::from_raw_parts(src.as_ptr() as *const u8, src.len() * 4);
+        surface.file.write_all(bytes).ok();
+    }
+
+    released.set(false);
+
+    let surface_proxy =
+        wayland_client::Proxy::<wl_surface::WlSurface>::from_c_ptr(wl_surface as *mut _);
+    let surface_proxy: Main<wl_surface::WlSurface> = surface_proxy.into();
+    surface_proxy.set_buffer_scale(scale_factor);
+    surface_proxy.attach(Some(&surface.buffers[surface.front]), 0, 0);
+    surface_proxy.damage_buffer(0, 0, width as i32, height as i32);
+    surface_proxy.commit();
+
+    surface.front = 1 - surface.front;
+    true
+}
+
+/// Attaches `buffer` (ARGB8888, `stride` pixels per row) to `wl_surface` and commits it,
+/// recreating the backing `wl_shm_pool` if its size doesn't match `width`/`height`.
+///
+/// `buffer` must hold at least `(height - 1) * stride + width` pixels (i.e. enough to read a
+/// full `width`x`height` image at `stride` pixels per row); if it's too short this is a no-op
+/// and returns `false` instead of indexing out of bounds.
+pub unsafe fn present_buffer(
+    wl_display: *mut c_void,
+    wl_surface: *mut c_void,
+    buffer: &[u32],
+    width: u32,
+    height: u32,
+    stride: u32,
+    scale_factor: i32,
+) -> bool {
+    let surface_id = wl_surface as usize;
+    SURFACES.with(|surfaces| {
+        let mut surfaces = surfaces.borrow_mut();
+        let needs_recreate = surfaces
+            .get(&surface_id)
+            .map_or(true, |s| s.width != width || s.height != height);
+        if needs_recreate {
+            match create_surface(wl_display, width, height) {
+                Some(surface) => {
+                    surfaces.insert(surface_id, surface);
+                }
+                None => return false,
+            }
+        }
+        let surface = surfaces.get_mut(&surface_id).unwrap();
+        blit(
+            surface,
+            wl_surface,
+            buffer,
+            width,
+            height,
+            stride,
+            scale_factor,
+        )
+    })
+}
+
+/// Replaces the backing pool for `wl_surface` with a newly allocated one of `width`x`height`
+/// ahead of the next `present_buffer` call at the new size. If the new pool can't be created,
+/// the existing one is kept.
+pub unsafe fn resize_surface(wl_display: *mut c_void, wl_surface: *mut c_void, width: u32, height: u32) {
+    let surface_id = wl_surface as usize;
+    if let Some(surface) = create_surface(wl_display, width, height) {
+        SURFACES.with(|surfaces| {
+            surfaces.borrow_mut().insert(surface_id, surface);
+        });
+    }
+}
+
+/// Drops the cached backing store for `wl_surface`, releasing its `wl_shm_pool` immediately
+/// instead of waiting for this thread's whole cache to tear down. Call this when the window is
+/// destroyed — see [`WindowExtUnix::destroy_shm_surface`](crate::platform::unix::WindowExtUnix::destroy_shm_surface).
+pub fn drop_surface(wl_surface: *mut c_void) {
+    let surface_id = wl_surface as usize;
+    SURFACES.with(|surfaces| {
+        surfaces.borrow_mut().remove(&surface_id);
+    });
+}
@@ -0,0 +1,303 @@
+//! MIT-SHM backed blit for `WindowExtUnix::present_buffer` on X11, falling back to a plain
+//! `XPutImage` when the X server doesn't advertise the `MIT-SHM` extension.
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
+use std::ptr;
+
+const ZPIXMAP: c_int = 2;
+
+#[repr(C)]
+struct XShmSegmentInfo {
+    shmseg: c_ulong,
+    shmid: c_int,
+    shmaddr: *mut c_char,
+    readOnly: c_int,
+}
+
+// Opaque stand-in for Xlib's `XImage`; we only ever read/write through the opaque
+// pointer `XShmCreateImage`/`XCreateImage` hand back to us, never its fields directly.
+enum XImage {}
+
+#[link(name = "X11")]
+extern "C" {
+    fn XDefaultVisual(display: *mut c_void, screen: c_int) -> *mut c_void;
+    fn XDefaultDepth(display: *mut c_void, screen: c_int) -> c_int;
+    fn XDefaultGC(display: *mut c_void, screen: c_int) -> *mut c_void;
+    fn XCreateImage(
+        display: *mut c_void,
+        visual: *mut c_void,
+        depth: c_uint,
+        format: c_int,
+        offset: c_int,
+        data: *mut c_char,
+        width: c_uint,
+        height: c_uint,
+        bitmap_pad: c_int,
+        bytes_per_line: c_int,
+    ) -> *mut XImage;
+    fn XPutImage(
+        display: *mut c_void,
+        drawable: c_ulong,
+        gc: *mut c_void,
+        image: *mut XImage,
+        src_x: c_int,
+        src_y: c_int,
+        dest_x: c_int,
+        dest_y: c_int,
+        width: c_uint,
+        height: c_uint,
+    ) -> c_int;
+    fn XDestroyImage(image: *mut XImage) -> c_int;
+    fn XSync(display: *mut c_void, discard: c_int) -> c_int;
+}
+
+#[link(name = "Xext")]
+extern "C" {
+    fn XShmQueryExtension(display: *mut c_void) -> c_int;
+    fn XShmCreateImage(
+        display: *mut c_void,
+        visual: *mut c_void,
+        depth: c_uint,
+        format: c_int,
+        data: *mut c_char,
+        shminfo: *mut XShmSegmentInfo,
+        width: c_uint,
+        height: c_uint,
+    ) -> *mut XImage;
+    fn XShmAttach(display: *mut c_void, shminfo: *mut XShmSegmentInfo) -> c_int;
+    fn XShmDetach(display: *mut c_void, shminfo: *mut XShmSegmentInfo) -> c_int;
+    fn XShmPutImage(
+        display: *mut c_void,
+        drawable: c_ulong,
+        gc: *mut c_void,
+        image: *mut XImage,
+        src_x: c_int,
+        src_y: c_int,
+        dest_x: c_int,
+        dest_y: c_int,
+        width: c_uint,
+        height: c_uint,
+        send_event: c_int,
+    ) -> c_int;
+}
+
+/// One window's backing store: either an attached SHM segment or, if the server lacks MIT-SHM,
+/// a plain heap buffer handed to `XPutImage` on every present.
+enum Backing {
+    Shm {
+        info: XShmSegmentInfo,
+        image: *mut XImage,
+    },
+    Plain {
+        data: Vec<c_char>,
+        image: *mut XImage,
+    },
+}
+
+struct Surface {
+    backing: Backing,
+    // Needed at drop time to detach the SHM segment/destroy the image on the right connection.
+    display: *mut c_void,
+    width: u32,
+    height: u32,
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        unsafe {
+            match &mut self.backing {
+                Backing::Shm { info, image } => {
+                    XShmDetach(self.display, info);
+                    XDestroyImage(*image);
+                    libc::shmdt(info.shmaddr as *const c_void);
+                }
+                Backing::Plain { image, .. } => {
+                    XDestroyImage(*image);
+                }
+            }
+        }
+    }
+}
+
+thread_local! {
+    // Keyed by the X window id: `present_buffer`/`resize_surface` are called repeatedly on the
+    // same `Window`, so we keep the SHM segment alive across calls instead of round-tripping to
+    // the server on every frame.
+    static SURFACES: RefCell<HashMap<c_ulong, Surface>> = RefCell::new(HashMap::new());
+}
+
+unsafe fn create_surface(display: *mut c_void, screen: c_int, width: u32, height: u32) -> Surface {
+    let visual = XDefaultVisual(display, screen);
+    let depth = XDefaultDepth(display, screen) as c_uint;
+    let len = (width * height * 4) as usize;
+
+    if XShmQueryExtension(display) != 0 {
+        let shmid = libc::shmget(
+            libc::IPC_PRIVATE,
+            len,
+            libc::IPC_CREAT | 0o600,
+        );
+        if shmid != -1 {
+            let shmaddr = libc::shmat(shmid, ptr::null(), 0);
+            if shmaddr as isize != -1 {
+                let mut info = XShmSegmentInfo {
+                    shmseg: 0,
+                    shmid,
+                    shmaddr: shmaddr as *mut c_char,
+                    readOnly: 0,
+                };
+                let image = XShmCreateImage(
+                    display,
+                    visual,
+                    depth,
+                    ZPIXMAP,
+                    info.shmaddr,
+                    &mut info,
+                    width,
+                    height,
+                );
+                if !image.is_null() && XShmAttach(display, &mut info) != 0 {
+                    // Marked for destruction as soon as the last attached client detaches; we
+                    // keep using the segment until then via `shmaddr`.
+                    libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut());
+                    return Surface {
+                        backing: Backing::Shm { info, image },
+                        display,
+                        width,
+                        height,
+                    };
+                }
+                libc::shmdt(shmaddr);
+            }
+            libc::shmctl(shmid, libc::IPC_RMID, ptr::null_mut());
+        }
+    }
+
+    // Fall back to a plain client-side image pushed through `XPutImage`.
+    let mut data = vec![0 as c_char; len];
+    let image = XCreateImage(
+        display,
+        visual,
+        depth,
+        ZPIXMAP,
+        0,
+        data.as_mut_ptr(),
+        width,
+        height,
+        32,
+        (width * 4) as c_int,
+    );
+    Surface {
+        backing: Backing::Plain { data, image },
+        display,
+        width,
+        height,
+    }
+}
+
+unsafe fn blit(
+    surface: &mut Surface,
+    display: *mut c_void,
+    window: c_ulong,
+    screen: c_int,
+    buffer: &[u32],
+    width: u32,
+    height: u32,
+    stride: u32,
+) -> bool {
+    if !copy_rows_fits(buffer.len(), width, height, stride) {
+        return false;
+    }
+
+    let gc = XDefaultGC(display, screen);
+    match &mut surface.backing {
+        Backing::Shm { info, image } => {
+            copy_rows(info.shmaddr as *mut u32, buffer, width, height, stride);
+            XShmPutImage(
+                display, window, gc, *image, 0, 0, 0, 0, width, height, 0,
+            );
+        }
+        Backing::Plain { data, image } => {
+            copy_rows(data.as_mut_ptr() as *mut u32, buffer, width, height, stride);
+            XPutImage(display, window, gc, *image, 0, 0, 0, 0, width, height);
+        }
+    }
+    XSync(display, 0);
+    true
+}
+
+/// Whether `buffer_len` pixels are enough to read a `width`x`height` image at `stride` pixels
+/// per row — i.e. whether `copy_rows` can run without reading out of bounds.
+fn copy_rows_fits(buffer_len: usize, width: u32, height: u32, stride: u32) -> bool {
+    if height == 0 {
+        return true;
+    }
+    if stride < width {
+        return false;
+    }
+    match (height as usize - 1)
+        .checked_mul(stride as usize)
+        .and_then(|rows| rows.checked_add(width as usize))
+    {
+        Some(required) => buffer_len >= required,
+        None => false,
+    }
+}
+
+unsafe fn copy_rows(dst: *mut u32, buffer: &[u32], width: u32, height: u32, stride: u32) {
+    for row in 0..height as usize {
+        let src = &buffer[row * stride as usize..row * stride as usize + width as usize];
+        ptr::copy_nonoverlapping(src.as_ptr(), dst.add(row * width as usize), width as usize);
+    }
+}
+
+/// Blits `buffer` (ARGB8888, `stride` pixels per row) to `window` via MIT-SHM, recreating the
+/// backing SHM segment if its size doesn't match `width`/`height`.
+///
+/// `buffer` must hold at least `(height - 1) * stride + width` pixels (i.e. enough to read a
+/// full `width`x`height` image at `stride` pixels per row); if it's too short this is a no-op
+/// and returns `false` instead of indexing out of bounds.
+pub unsafe fn present_buffer(
+    display: *mut c_void,
+    window: c_ulong,
+    screen: c_int,
+    buffer: &[u32],
+    width: u32,
+    height: u32,
+    stride: u32,
+) -> bool {
+    SURFACES.with(|surfaces| {
+        let mut surfaces = surfaces.borrow_mut();
+        let needs_recreate = surfaces
+            .get(&window)
+            .map_or(true, |s| s.width != width || s.height != height);
+        if needs_recreate {
+            surfaces.insert(window, create_surface(display, screen, width, height));
+        }
+        let surface = surfaces.get_mut(&window).unwrap();
+        blit(surface, display, window, screen, buffer, width, height, stride)
+    })
+}
+
+/// Replaces the backing store for `window` with a newly allocated one of `width`x`height`
+/// ahead of the next `present_buffer` call at the new size, dropping the previous one.
+pub unsafe fn resize_surface(display: *mut c_void, window: c_ulong, screen: c_int, width: u32, height: u32) {
+    SURFACES.with(|surfaces| {
+        surfaces
+            .borrow_mut()
+            .insert(window, create_surface(display, screen, width, height));
+    });
+}
+
+/// Drops the cached backing store for `window`, releasing its SHM segment immediately instead
+/// of waiting for this thread's whole cache to tear down. Call this when the window is
+/// destroyed — see [`WindowExtUnix::destroy_shm_surface`](crate::platform::unix::WindowExtUnix::destroy_shm_surface).
+pub fn drop_surface(window: c_ulong) {
+    SURFACES.with(|surfaces| {
+        surfaces.borrow_mut().remove(&window);
+    });
+}
@@ -0,0 +1,139 @@
+//! Raw FFI bindings to the subset of `libxkbcommon` (and its `-x11`/`-compose`
+//! companions) needed by [`super::xkb_keysyms`].
+//!
+//! This mirrors the small, hand-rolled `xkb_ffi` module that the minifb backend
+//! carries rather than pulling in a full `xkbcommon-sys` dependency: winit only
+//! ever touches a handful of entry points, so the bindings are kept narrow and
+//! `#[allow(non_camel_case_types)]`/`#[allow(dead_code)]` where upstream names
+//! demand it.
+
+#![allow(non_camel_case_types, dead_code)]
+
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+pub const XKB_KEYMAP_COMPILE_NO_FLAGS: u32 = 0;
+pub const XKB_CONTEXT_NO_FLAGS: u32 = 0;
+pub const XKB_KEYMAP_FORMAT_TEXT_V1: u32 = 1;
+
+pub const XKB_COMPOSE_FORMAT_TEXT_V1: u32 = 1;
+pub const XKB_COMPOSE_COMPILE_NO_FLAGS: u32 = 0;
+pub const XKB_COMPOSE_STATE_NO_FLAGS: u32 = 0;
+
+/// Mirrors `enum xkb_compose_status`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum xkb_compose_status {
+    XKB_COMPOSE_NOTHING = 0,
+    XKB_COMPOSE_COMPOSING = 1,
+    XKB_COMPOSE_COMPOSED = 2,
+    XKB_COMPOSE_CANCELLED = 3,
+}
+
+/// Mirrors `enum xkb_compose_feed_result`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum xkb_compose_feed_result {
+    XKB_COMPOSE_FEED_IGNORED = 0,
+    XKB_COMPOSE_FEED_ACCEPTED = 1,
+}
+
+/// Mirrors `enum xkb_state_component`, the bitmask `xkb_state_update_mask` returns to report
+/// which parts of the state changed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct xkb_state_component(pub c_uint);
+
+pub enum xkb_context {}
+pub enum xkb_keymap {}
+pub enum xkb_state {}
+pub enum xkb_compose_table {}
+pub enum xkb_compose_state {}
+
+#[link(name = "xkbcommon")]
+extern "C" {
+    pub fn xkb_context_new(flags: u32) -> *mut xkb_context;
+    pub fn xkb_context_unref(context: *mut xkb_context);
+
+    pub fn xkb_keymap_new_from_string(
+        context: *mut xkb_context,
+        string: *const c_char,
+        format: u32,
+        flags: u32,
+    ) -> *mut xkb_keymap;
+    pub fn xkb_keymap_unref(keymap: *mut xkb_keymap);
+
+    pub fn xkb_state_new(keymap: *mut xkb_keymap) -> *mut xkb_state;
+    pub fn xkb_state_unref(state: *mut xkb_state);
+
+    /// Returns the single keysym obtained from the modifier/group state currently latched
+    /// into `state` for the given (X11-numbered, i.e. evdev + 8) keycode.
+    pub fn xkb_state_key_get_one_sym(state: *mut xkb_state, key: u32) -> u32;
+
+    /// Writes the UTF-8 text (if any) produced by `key` under the current state into `buffer`,
+    /// NUL-terminated, truncated to `size`. Returns the length that would have been written
+    /// (not including the NUL), `-1` on error, matching upstream's `snprintf`-style contract.
+    pub fn xkb_state_key_get_utf8(
+        state: *mut xkb_state,
+        key: u32,
+        buffer: *mut c_char,
+        size: usize,
+    ) -> c_int;
+
+    /// Feeds the depressed/latched/locked modifier and group masks (as reported by the
+    /// compositor's `wl_keyboard.modifiers` event, or by `XkbSelectEvents` on X11) into `state`.
+    pub fn xkb_state_update_mask(
+        state: *mut xkb_state,
+        depressed_mods: u32,
+        latched_mods: u32,
+        locked_mods: u32,
+        depressed_layout: u32,
+        latched_layout: u32,
+        locked_layout: u32,
+    ) -> xkb_state_component;
+}
+
+#[link(name = "xkbcommon-x11")]
+extern "C" {
+    /// Builds a keymap for the XKB keyboard `device_id` (as returned by
+    /// [`xkb_x11_get_core_keyboard_device_id`]) over an already-open `xcb_connection_t`.
+    pub fn xkb_x11_keymap_new_from_device(
+        context: *mut xkb_context,
+        connection: *mut c_void,
+        device_id: i32,
+        flags: u32,
+    ) -> *mut xkb_keymap;
+
+    pub fn xkb_x11_state_new_from_device(
+        keymap: *mut xkb_keymap,
+        connection: *mut c_void,
+        device_id: i32,
+    ) -> *mut xkb_state;
+
+    /// Returns the XKB-extension device id of the core keyboard, as expected by
+    /// [`xkb_x11_keymap_new_from_device`].
+    pub fn xkb_x11_get_core_keyboard_device_id(connection: *mut c_void) -> i32;
+}
+
+#[link(name = "xkbcommon-compose")]
+extern "C" {
+    pub fn xkb_compose_table_new_from_locale(
+        context: *mut xkb_context,
+        locale: *const c_char,
+        flags: u32,
+    ) -> *mut xkb_compose_table;
+    pub fn xkb_compose_table_unref(table: *mut xkb_compose_table);
+
+    pub fn xkb_compose_state_new(table: *mut xkb_compose_table, flags: u32)
+        -> *mut xkb_compose_state;
+    pub fn xkb_compose_state_unref(state: *mut xkb_compose_state);
+
+    pub fn xkb_compose_state_feed(state: *mut xkb_compose_state, keysym: u32)
+        -> xkb_compose_feed_result;
+    pub fn xkb_compose_state_get_status(state: *mut xkb_compose_state) -> xkb_compose_status;
+    pub fn xkb_compose_state_get_utf8(
+        state: *mut xkb_compose_state,
+        buffer: *mut c_char,
+        size: usize,
+    ) -> c_int;
+    pub fn xkb_compose_state_reset(state: *mut xkb_compose_state);
+}
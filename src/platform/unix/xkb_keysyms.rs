@@ -0,0 +1,297 @@
+//! Layout-aware keysym, text and compose-sequence resolution on top of
+//! [`super::xkb_ffi`].
+//!
+//! **Not wired up yet.** Nothing in this crate calls [`XkbState::from_wayland_keymap`],
+//! [`XkbState::from_x11_device`] or [`XkbState::key`] — there is no Wayland or X11 event path
+//! that builds an `XkbState` from a real keymap and feeds key codes through it. This module is
+//! scaffolding for that integration, not a working translation layer; `KeyCodeExtScancode` in
+//! `super::super` is untouched by it and still only resolves the physical evdev scancode.
+//!
+//! [`XkbState`] owns one `xkb_context`/`xkb_keymap`/`xkb_state`, built either from the
+//! keymap fd a Wayland compositor sends via `wl_keyboard.keymap`
+//! ([`XkbState::from_wayland_keymap`]) or from the X server's active keymap via
+//! `xkb_x11_keymap_new_from_device` ([`XkbState::from_x11_device`]). Per key event, feed the
+//! raw (X11-numbered) keycode to [`XkbState::key`] to get the logical [`Key`] and any UTF-8
+//! text, with dead keys and compose sequences resolved through an `xkb_compose_state`; feed
+//! modifier changes through [`XkbState::update_mask`].
+
+#![allow(dead_code)]
+
+use std::ffi::CString;
+use std::io;
+use std::os::raw::c_void;
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+use super::xkb_ffi::*;
+use crate::keyboard::{Key, NativeKeyCode};
+
+/// The physical-to-logical key translator for one keyboard device.
+///
+/// Dropping this frees the underlying `xkb_state`/`xkb_keymap`/`xkb_context` (and the compose
+/// state, if any).
+pub struct XkbState {
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+    compose_state: Option<*mut xkb_compose_state>,
+}
+
+// The xkbcommon objects are only ever touched from the thread that owns the event loop that
+// drives the compositor/X connection they were created against.
+unsafe impl Send for XkbState {}
+
+impl XkbState {
+    /// Builds keyboard state from the keymap `wl_keyboard.keymap` handed the client: a
+    /// memory-mapped, NUL-terminated, XKB-text-v1 keymap description of `size` bytes backed by
+    /// `fd`.
+    pub fn from_wayland_keymap(fd: RawFd, size: usize) -> io::Result<Self> {
+        unsafe {
+            let map = libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                fd,
+                0,
+            );
+            if map == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+
+            let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                libc::munmap(map, size);
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "xkb_context_new failed",
+                ));
+            }
+
+            let keymap = xkb_keymap_new_from_string(
+                context,
+                map as *const _,
+                XKB_KEYMAP_FORMAT_TEXT_V1,
+                XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            libc::munmap(map, size);
+
+            if keymap.is_null() {
+                xkb_context_unref(context);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "compositor sent an unparseable XKB keymap",
+                ));
+            }
+
+            Ok(Self::from_context_and_keymap(context, keymap))
+        }
+    }
+
+    /// Builds keyboard state from the core keyboard device's keymap on an open
+    /// `xcb_connection_t`.
+    ///
+    /// Returns `None` if the X server doesn't support the XKB extension or has no core keyboard
+    /// device.
+    pub fn from_x11_device(xcb_connection: *mut c_void) -> Option<Self> {
+        unsafe {
+            let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+
+            let device_id = xkb_x11_get_core_keyboard_device_id(xcb_connection);
+            if device_id < 0 {
+                xkb_context_unref(context);
+                return None;
+            }
+
+            let keymap = xkb_x11_keymap_new_from_device(
+                context,
+                xcb_connection,
+                device_id,
+                XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            if keymap.is_null() {
+                xkb_context_unref(context);
+                return None;
+            }
+
+            Some(Self::from_context_and_keymap(context, keymap))
+        }
+    }
+
+    unsafe fn from_context_and_keymap(
+        context: *mut xkb_context,
+        keymap: *mut xkb_keymap,
+    ) -> Self {
+        let state = xkb_state_new(keymap);
+        Self {
+            context,
+            keymap,
+            state,
+            compose_state: Self::new_compose_state(context),
+        }
+    }
+
+    unsafe fn new_compose_state(context: *mut xkb_context) -> Option<*mut xkb_compose_state> {
+        let locale = std::env::var("LC_ALL")
+            .or_else(|_| std::env::var("LC_CTYPE"))
+            .or_else(|_| std::env::var("LANG"))
+            .unwrap_or_else(|_| "C".to_owned());
+        let locale = CString::new(locale).ok()?;
+
+        let table = xkb_compose_table_new_from_locale(
+            context,
+            locale.as_ptr(),
+            XKB_COMPOSE_COMPILE_NO_FLAGS,
+        );
+        if table.is_null() {
+            return None;
+        }
+
+        let state = xkb_compose_state_new(table, XKB_COMPOSE_STATE_NO_FLAGS);
+        xkb_compose_table_unref(table);
+        if state.is_null() {
+            None
+        } else {
+            Some(state)
+        }
+    }
+
+    /// Feeds the modifier/group masks from a `wl_keyboard.modifiers` event (or the equivalent
+    /// XKB state-notify event on X11) into the translator.
+    pub fn update_mask(
+        &mut self,
+        depressed_mods: u32,
+        latched_mods: u32,
+        locked_mods: u32,
+        depressed_layout: u32,
+        latched_layout: u32,
+        locked_layout: u32,
+    ) {
+        unsafe {
+            xkb_state_update_mask(
+                self.state,
+                depressed_mods,
+                latched_mods,
+                locked_mods,
+                depressed_layout,
+                latched_layout,
+                locked_layout,
+            );
+        }
+    }
+
+    /// Resolves the X11-numbered keycode (evdev code + 8) of a key event to the logical [`Key`]
+    /// it produces, along with any UTF-8 text, running the result through the compose state so
+    /// dead keys and compose sequences collapse into their composed character.
+    ///
+    /// Returns `(None, None)` while a compose sequence is still in progress: the caller should
+    /// not treat the key press as producing a character yet.
+    pub fn key(&mut self, xkb_keycode: u32) -> (Option<Key>, Option<String>) {
+        let keysym = unsafe { xkb_state_key_get_one_sym(self.state, xkb_keycode) };
+        let text = self.utf8_for_key(xkb_keycode);
+
+        let composed = self.compose_state.map(|compose_state| unsafe {
+            let feed = xkb_compose_state_feed(compose_state, keysym);
+            if feed != xkb_compose_feed_result::XKB_COMPOSE_FEED_ACCEPTED {
+                return Ok(text.clone());
+            }
+            match xkb_compose_state_get_status(compose_state) {
+                xkb_compose_status::XKB_COMPOSE_COMPOSING => Err(()),
+                xkb_compose_status::XKB_COMPOSE_CANCELLED => {
+                    xkb_compose_state_reset(compose_state);
+                    Err(())
+                }
+                xkb_compose_status::XKB_COMPOSE_COMPOSED => {
+                    Ok(self.utf8_from_compose_state(compose_state))
+                }
+                xkb_compose_status::XKB_COMPOSE_NOTHING => Ok(text.clone()),
+            }
+        });
+
+        match composed {
+            Some(Ok(text)) | None => (keysym_to_key(keysym), text),
+            Some(Err(())) => (None, None),
+        }
+    }
+
+    fn utf8_for_key(&self, xkb_keycode: u32) -> Option<String> {
+        unsafe {
+            let len = xkb_state_key_get_utf8(self.state, xkb_keycode, ptr::null_mut(), 0);
+            if len <= 0 {
+                return None;
+            }
+            let mut buffer = vec![0u8; len as usize + 1];
+            xkb_state_key_get_utf8(
+                self.state,
+                xkb_keycode,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len(),
+            );
+            buffer.truncate(len as usize);
+            String::from_utf8(buffer).ok()
+        }
+    }
+
+    unsafe fn utf8_from_compose_state(
+        &self,
+        compose_state: *mut xkb_compose_state,
+    ) -> Option<String> {
+        let len = xkb_compose_state_get_utf8(compose_state, ptr::null_mut(), 0);
+        if len <= 0 {
+            return None;
+        }
+        let mut buffer = vec![0u8; len as usize + 1];
+        xkb_compose_state_get_utf8(compose_state, buffer.as_mut_ptr() as *mut _, buffer.len());
+        buffer.truncate(len as usize);
+        String::from_utf8(buffer).ok()
+    }
+}
+
+impl Drop for XkbState {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(compose_state) = self.compose_state {
+                xkb_compose_state_unref(compose_state);
+            }
+            xkb_state_unref(self.state);
+            xkb_keymap_unref(self.keymap);
+            xkb_context_unref(self.context);
+        }
+    }
+}
+
+/// Maps an X11 keysym to winit's layout-independent [`Key`].
+///
+/// This only needs to cover the keysyms that aren't already resolved through
+/// `utf8_for_key`'s printable text, i.e. the non-printable/control keys (plus Space); keysym 0
+/// (`NoSymbol`) maps to `None` and every other keysym falls back to [`Key::Unidentified`].
+fn keysym_to_key(keysym: u32) -> Option<Key> {
+    // Keysym values from <xkbcommon/xkbcommon-keysyms.h>.
+    match keysym {
+        0xff1b => Some(Key::Escape),
+        0xff08 => Some(Key::Backspace),
+        0xff09 => Some(Key::Tab),
+        0xff0d => Some(Key::Enter),
+        0x0020 => Some(Key::Space),
+        0xffe1 => Some(Key::Shift),
+        0xffe2 => Some(Key::Shift),
+        0xffe3 => Some(Key::Control),
+        0xffe4 => Some(Key::Control),
+        0xffe9 => Some(Key::Alt),
+        0xffea => Some(Key::Alt),
+        0xff50 => Some(Key::Home),
+        0xff57 => Some(Key::End),
+        0xff55 => Some(Key::PageUp),
+        0xff56 => Some(Key::PageDown),
+        0xff51 => Some(Key::ArrowLeft),
+        0xff52 => Some(Key::ArrowUp),
+        0xff53 => Some(Key::ArrowRight),
+        0xff54 => Some(Key::ArrowDown),
+        0xffff => Some(Key::Delete),
+        0 => None,
+        _ => Some(Key::Unidentified(NativeKeyCode::XKB(keysym))),
+    }
+}